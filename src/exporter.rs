@@ -1,6 +1,9 @@
 use crate::unifi::DeviceStats;
 use anyhow::Result;
-use prometheus::{Encoder, GaugeVec, Registry, TextEncoder};
+use prometheus::{Counter, Encoder, Gauge, GaugeVec, Registry, TextEncoder};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub struct MetricsExporter {
     pub registry: Registry,
@@ -12,6 +15,33 @@ pub struct MetricsExporter {
     pub load_15: GaugeVec,
     pub tx_rate: GaugeVec,
     pub rx_rate: GaugeVec,
+    pub radio_tx_retries_pct: GaugeVec,
+    pub radio_up: GaugeVec,
+    pub device_up: GaugeVec,
+    pub scrape_errors_total: Counter,
+    pub scrape_duration_seconds: Gauge,
+    pub last_scrape_success_timestamp: Gauge,
+    ready: AtomicBool,
+    /// `[site, device, role, location]` label sets touched so far this scrape
+    /// cycle, cleared by `begin_scrape`.
+    device_labels_touched: Mutex<HashSet<[String; 4]>>,
+    /// The same, but from the last scrape that `commit_scrape` was told succeeded.
+    device_labels_seen: Mutex<HashSet<[String; 4]>>,
+    /// `[site, device, frequency_ghz, role, location]` label sets touched so far
+    /// this scrape cycle, cleared by `begin_scrape`.
+    radio_labels_touched: Mutex<HashSet<[String; 5]>>,
+    /// The same, but from the last scrape that `commit_scrape` was told succeeded.
+    radio_labels_seen: Mutex<HashSet<[String; 5]>>,
+}
+
+/// Formats a radio frequency as a label value, e.g. `2.4`, `5`, `6` - dropping a
+/// trailing `.0` so whole-GHz bands don't render as `5.0`.
+fn format_frequency_ghz(frequency_ghz: f64) -> String {
+    if frequency_ghz.fract() == 0.0 {
+        format!("{}", frequency_ghz as i64)
+    } else {
+        format!("{}", frequency_ghz)
+    }
 }
 
 impl MetricsExporter {
@@ -20,42 +50,88 @@ impl MetricsExporter {
 
         let cpu_util = GaugeVec::new(
             prometheus::Opts::new("unifi_device_cpu_utilization_pct", "CPU usage (%)"),
-            &["device"],
+            &["site", "device", "role", "location"],
         )?;
         let mem_util = GaugeVec::new(
             prometheus::Opts::new("unifi_device_memory_utilization_pct", "Memory usage (%)"),
-            &["device"],
+            &["site", "device", "role", "location"],
         )?;
         let uptime = GaugeVec::new(
             prometheus::Opts::new("unifi_device_uptime_seconds", "Uptime in seconds"),
-            &["device"],
+            &["site", "device", "role", "location"],
         )?;
         let load_1 = GaugeVec::new(
             prometheus::Opts::new("unifi_device_load_average_1min", "Load avg over 1min"),
-            &["device"],
+            &["site", "device", "role", "location"],
         )?;
         let load_5 = GaugeVec::new(
             prometheus::Opts::new("unifi_device_load_average_5min", "Load avg over 5min"),
-            &["device"],
+            &["site", "device", "role", "location"],
         )?;
         let load_15 = GaugeVec::new(
             prometheus::Opts::new("unifi_device_load_average_15min", "Load avg over 15min"),
-            &["device"],
+            &["site", "device", "role", "location"],
         )?;
         let tx_rate = GaugeVec::new(
             prometheus::Opts::new("unifi_device_tx_rate_bps", "TX rate in bps"),
-            &["device"],
+            &["site", "device", "role", "location"],
         )?;
         let rx_rate = GaugeVec::new(
             prometheus::Opts::new("unifi_device_rx_rate_bps", "RX rate in bps"),
-            &["device"],
+            &["site", "device", "role", "location"],
+        )?;
+        let radio_tx_retries_pct = GaugeVec::new(
+            prometheus::Opts::new(
+                "unifi_radio_tx_retries_pct",
+                "Radio TX retry percentage",
+            ),
+            &["site", "device", "frequency_ghz", "role", "location"],
+        )?;
+        let radio_up = GaugeVec::new(
+            prometheus::Opts::new(
+                "unifi_radio_up",
+                "Set to 1 for each radio present in the latest scrape; a radio absent from a scrape has its series removed rather than set to 0",
+            ),
+            &["site", "device", "frequency_ghz", "role", "location"],
+        )?;
+        let device_up = GaugeVec::new(
+            prometheus::Opts::new(
+                "unifi_device_up",
+                "Whether a device is reachable and reported healthy stats in the latest scrape (1) or not (0)",
+            ),
+            &["site", "device", "role", "location"],
+        )?;
+        let scrape_errors_total = Counter::new(
+            "unifi_scrape_errors_total",
+            "Total number of device stats and site device-listing fetch/deserialize failures across all scrapes",
+        )?;
+        let scrape_duration_seconds = Gauge::new(
+            "unifi_scrape_duration_seconds",
+            "Duration of the last scrape cycle in seconds",
+        )?;
+        let last_scrape_success_timestamp = Gauge::new(
+            "unifi_last_scrape_success_timestamp",
+            "Unix timestamp of the last successful scrape",
         )?;
 
         for metric in [
-            &cpu_util, &mem_util, &uptime, &load_1, &load_5, &load_15, &tx_rate, &rx_rate,
+            &cpu_util,
+            &mem_util,
+            &uptime,
+            &load_1,
+            &load_5,
+            &load_15,
+            &tx_rate,
+            &rx_rate,
+            &radio_tx_retries_pct,
+            &radio_up,
+            &device_up,
         ] {
             registry.register(Box::new(metric.clone()))?;
         }
+        registry.register(Box::new(scrape_errors_total.clone()))?;
+        registry.register(Box::new(scrape_duration_seconds.clone()))?;
+        registry.register(Box::new(last_scrape_success_timestamp.clone()))?;
 
         Ok(Self {
             registry,
@@ -67,34 +143,172 @@ impl MetricsExporter {
             load_15,
             tx_rate,
             rx_rate,
+            radio_tx_retries_pct,
+            radio_up,
+            device_up,
+            scrape_errors_total,
+            scrape_duration_seconds,
+            last_scrape_success_timestamp,
+            ready: AtomicBool::new(false),
+            device_labels_touched: Mutex::new(HashSet::new()),
+            device_labels_seen: Mutex::new(HashSet::new()),
+            radio_labels_touched: Mutex::new(HashSet::new()),
+            radio_labels_seen: Mutex::new(HashSet::new()),
         })
     }
 
-    pub fn update_device_metrics(&self, device_name: &str, stats: &DeviceStats) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_device_metrics(
+        &self,
+        site_name: &str,
+        device_name: &str,
+        role: &str,
+        location: &str,
+        stats: &DeviceStats,
+    ) {
+        let labels = [site_name, device_name, role, location];
+
         self.cpu_util
-            .with_label_values(&[device_name])
+            .with_label_values(&labels)
             .set(stats.cpu_utilization_pct);
         self.mem_util
-            .with_label_values(&[device_name])
+            .with_label_values(&labels)
             .set(stats.memory_utilization_pct);
         self.uptime
-            .with_label_values(&[device_name])
+            .with_label_values(&labels)
             .set(stats.uptime_sec as f64);
         self.load_1
-            .with_label_values(&[device_name])
-            .set(stats.load_average_1min);
+            .with_label_values(&labels)
+            .set(stats.load_average_1_min);
         self.load_5
-            .with_label_values(&[device_name])
-            .set(stats.load_average_5min);
+            .with_label_values(&labels)
+            .set(stats.load_average_5_min);
         self.load_15
-            .with_label_values(&[device_name])
-            .set(stats.load_average_15min);
+            .with_label_values(&labels)
+            .set(stats.load_average_15_min);
         self.tx_rate
-            .with_label_values(&[device_name])
+            .with_label_values(&labels)
             .set(stats.uplink.tx_rate_bps as f64);
         self.rx_rate
-            .with_label_values(&[device_name])
+            .with_label_values(&labels)
             .set(stats.uplink.rx_rate_bps as f64);
+        self.device_labels_touched
+            .lock()
+            .unwrap()
+            .insert(labels.map(String::from));
+
+        if let Some(radios) = &stats.interfaces.radios {
+            for radio in radios {
+                let frequency_ghz = format_frequency_ghz(radio.frequency_ghz);
+                let radio_labels = [site_name, device_name, &frequency_ghz, role, location];
+                self.radio_tx_retries_pct
+                    .with_label_values(&radio_labels)
+                    .set(radio.tx_retries_pct);
+                self.radio_up
+                    .with_label_values(&radio_labels)
+                    .set(1.0);
+                self.radio_labels_touched
+                    .lock()
+                    .unwrap()
+                    .insert(radio_labels.map(String::from));
+            }
+        }
+    }
+
+    /// Sets `unifi_device_up` directly, e.g. when a device's reported `state` marks
+    /// it offline even though its stats fetch otherwise succeeded.
+    pub fn set_device_up(&self, site_name: &str, device_name: &str, role: &str, location: &str, up: bool) {
+        let labels = [site_name, device_name, role, location];
+        self.device_up.with_label_values(&labels).set(if up { 1.0 } else { 0.0 });
+        self.device_labels_touched
+            .lock()
+            .unwrap()
+            .insert(labels.map(String::from));
+    }
+
+    /// Marks a device as unreachable (its stats fetch or deserialize failed this
+    /// scrape) and bumps `unifi_scrape_errors_total`, so a single bad device shows
+    /// up in metrics instead of aborting the whole scrape.
+    pub fn mark_device_down(&self, site_name: &str, device_name: &str, role: &str, location: &str) {
+        self.set_device_up(site_name, device_name, role, location, false);
+        self.scrape_errors_total.inc();
+    }
+
+    /// Bumps `unifi_scrape_errors_total` for a failure that isn't tied to a single
+    /// device, e.g. a site's device listing failing to fetch or deserialize.
+    pub fn record_scrape_error(&self) {
+        self.scrape_errors_total.inc();
+    }
+
+    /// Whether at least one scrape has completed successfully. Backs `/health` so
+    /// container orchestrators can gate readiness on real data being available.
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Starts a new scrape cycle by clearing the set of device/radio label sets
+    /// touched so far. Call once before the first `update_device_metrics`/
+    /// `set_device_up` of a cycle; this does not touch the gauges themselves,
+    /// so a failed cycle leaves `/metrics` serving
+    /// the last good values instead of blanking them up front.
+    pub fn begin_scrape(&self) {
+        self.device_labels_touched.lock().unwrap().clear();
+        self.radio_labels_touched.lock().unwrap().clear();
+    }
+
+    /// Finishes a scrape cycle. On success, any device/radio label set that was
+    /// present last successful cycle but wasn't touched this cycle (e.g. a
+    /// decommissioned or renamed device, or a radio that disappeared) is removed
+    /// from the gauges instead of lingering forever, and the touched set becomes
+    /// the new baseline. On failure, gauges and the baseline are left untouched.
+    pub fn commit_scrape(&self, success: bool) {
+        if !success {
+            return;
+        }
+
+        let touched = self.device_labels_touched.lock().unwrap().clone();
+        let mut seen = self.device_labels_seen.lock().unwrap();
+        for stale in seen.difference(&touched) {
+            let labels: Vec<&str> = stale.iter().map(String::as_str).collect();
+            for gauge in [
+                &self.cpu_util,
+                &self.mem_util,
+                &self.uptime,
+                &self.load_1,
+                &self.load_5,
+                &self.load_15,
+                &self.tx_rate,
+                &self.rx_rate,
+                &self.device_up,
+            ] {
+                let _ = gauge.remove_label_values(&labels);
+            }
+        }
+        *seen = touched;
+
+        let touched = self.radio_labels_touched.lock().unwrap().clone();
+        let mut seen = self.radio_labels_seen.lock().unwrap();
+        for stale in seen.difference(&touched) {
+            let labels: Vec<&str> = stale.iter().map(String::as_str).collect();
+            let _ = self.radio_tx_retries_pct.remove_label_values(&labels);
+            let _ = self.radio_up.remove_label_values(&labels);
+        }
+        *seen = touched;
+    }
+
+    /// Records the outcome of a scrape cycle. `duration` is always recorded; the
+    /// success timestamp is bumped and `/health` marked ready only when the cycle
+    /// completed without error.
+    pub fn record_scrape(&self, duration: std::time::Duration, success: bool) {
+        self.scrape_duration_seconds.set(duration.as_secs_f64());
+        if success {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or_default();
+            self.last_scrape_success_timestamp.set(now);
+            self.ready.store(true, Ordering::Relaxed);
+        }
     }
 
     pub fn render(&self) -> Result<String> {
@@ -104,3 +318,19 @@ impl MetricsExporter {
         Ok(String::from_utf8(buffer)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_trailing_zero_for_whole_ghz_bands() {
+        assert_eq!(format_frequency_ghz(5.0), "5");
+        assert_eq!(format_frequency_ghz(6.0), "6");
+    }
+
+    #[test]
+    fn keeps_fractional_ghz_bands() {
+        assert_eq!(format_frequency_ghz(2.4), "2.4");
+    }
+}
@@ -1,14 +1,23 @@
 mod exporter;
+mod relabel;
 mod unifi;
 
 use ::clap::Parser;
 use anyhow::anyhow;
 use exporter::MetricsExporter;
+use relabel::RelabelConfig;
 use std::env;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use unifi::UnifiClient;
 
 use actix_web::{App, HttpResponse, HttpServer, web};
 
+/// Default interval between background scrapes when neither `--interval-secs`
+/// nor `UNIFI_POLL_INTERVAL` is set.
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -19,8 +28,17 @@ struct Args {
     /// Unifi API Token
     #[arg(short, long)]
     token: Option<String>,
+
+    /// Seconds between background metric scrapes
+    #[arg(long)]
+    interval_secs: Option<u64>,
+
+    /// Path to a TOML or YAML file mapping device IDs/MAC addresses to friendly
+    /// names and extra labels (role, location)
+    #[arg(long)]
+    config: Option<String>,
 }
-fn load_config() -> Result<(String, String), anyhow::Error> {
+fn load_config() -> Result<(String, String, u64, Option<String>), anyhow::Error> {
     let args = Args::parse();
 
     let endpoint = args
@@ -39,12 +57,26 @@ fn load_config() -> Result<(String, String), anyhow::Error> {
             anyhow!("UNIFI_API_TOKEN not provided. Please pass --token or set UNIFI_API_TOKEN")
         })?;
 
-    Ok((endpoint, token))
+    let interval_secs = args
+        .interval_secs
+        .or_else(|| {
+            env::var("UNIFI_POLL_INTERVAL")
+                .ok()
+                .and_then(|v| v.parse().ok())
+        })
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+
+    let config_path = args.config.or_else(|| env::var("UNIFI_RELABEL_CONFIG").ok());
+
+    Ok((endpoint, token, interval_secs, config_path))
 }
 
-async fn fetch_devices(client: &UnifiClient) -> Result<unifi::DevicesResponse, anyhow::Error> {
+async fn fetch_devices(
+    client: &UnifiClient,
+    site_id: &str,
+) -> Result<unifi::DevicesResponse, anyhow::Error> {
     println!("🔍 Fetching devices...");
-    let raw_devices = client.get_devices().await?;
+    let raw_devices = client.get_devices(site_id).await?;
 
     let devices: unifi::DevicesResponse = serde_json::from_value(raw_devices)
         .map_err(|e| anyhow!("Failed to deserialize devices response: {}", e))?;
@@ -57,49 +89,171 @@ async fn fetch_devices(client: &UnifiClient) -> Result<unifi::DevicesResponse, a
     Ok(devices)
 }
 
+/// Fetches and deserializes a single device's stats.
+async fn fetch_device_stats(
+    client: &UnifiClient,
+    site_id: &str,
+    device_id: &str,
+) -> Result<unifi::DeviceStats, anyhow::Error> {
+    let raw_device_stats = client.get_device_stats(site_id, device_id).await?;
+    serde_json::from_value(raw_device_stats)
+        .map_err(|e| anyhow!("Failed to deserialize device stats response: {}", e))
+}
+
+/// Runs one `get_all_sites` -> `get_devices` -> `get_device_stats` -> `update_device_metrics`
+/// cycle across every site the controller reports, rather than a single hardwired site.
+/// A single site's device listing, or a single device's stats, failing to fetch or
+/// deserialize is logged and counted via `unifi_scrape_errors_total` rather than
+/// aborting the whole cycle.
+async fn poll_devices(
+    client: &UnifiClient,
+    exporter: &MetricsExporter,
+    relabel: &RelabelConfig,
+) -> Result<(), anyhow::Error> {
+    exporter.begin_scrape();
+    let sites = client.get_all_sites().await?;
+
+    for site in &sites.data {
+        let devices = match fetch_devices(client, &site.id).await {
+            Ok(devices) => devices,
+            Err(e) => {
+                eprintln!(
+                    "⚠️ Failed to fetch devices for site '{}': {}",
+                    site.name, e
+                );
+                exporter.record_scrape_error();
+                continue;
+            }
+        };
+
+        for dev in &devices.data {
+            let resolved = relabel.resolve(&dev.id, &dev.mac_address, dev.name.as_str());
+            let is_online = dev.state.eq_ignore_ascii_case("online");
+
+            match fetch_device_stats(client, &site.id, &dev.id.to_string()).await {
+                Ok(device_stats) => {
+                    exporter.update_device_metrics(
+                        site.name.as_str(),
+                        &resolved.name,
+                        &resolved.role,
+                        &resolved.location,
+                        &device_stats,
+                    );
+                    exporter.set_device_up(
+                        site.name.as_str(),
+                        &resolved.name,
+                        &resolved.role,
+                        &resolved.location,
+                        is_online,
+                    );
+                }
+                Err(e) => {
+                    eprintln!(
+                        "⚠️ Failed to fetch stats for device '{}': {}",
+                        resolved.name, e
+                    );
+                    exporter.mark_device_down(
+                        site.name.as_str(),
+                        &resolved.name,
+                        &resolved.role,
+                        &resolved.location,
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Background task that keeps `/metrics` fresh by re-scraping on a fixed interval.
+/// A single failed cycle is logged and skipped rather than taking the exporter down.
+async fn poll_loop(
+    client: Arc<UnifiClient>,
+    exporter: web::Data<MetricsExporter>,
+    relabel: Arc<RelabelConfig>,
+    interval: Duration,
+) {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let start = Instant::now();
+        let result = poll_devices(&client, &exporter, &relabel).await;
+        exporter.record_scrape(start.elapsed(), result.is_ok());
+        exporter.commit_scrape(result.is_ok());
+
+        if let Err(e) = result {
+            eprintln!("⚠️ Scrape failed, keeping previous metric values: {}", e);
+        }
+    }
+}
+
 async fn serve_metrics(
     exporter: web::Data<MetricsExporter>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let body = exporter
         .render()
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e))?;
+        .map_err(actix_web::error::ErrorInternalServerError)?;
 
     Ok(HttpResponse::Ok()
         .content_type("text/plain; version=0.0.4; charset=utf-8")
         .body(body))
 }
 
+/// Readiness probe: only reports 200 once at least one scrape has completed
+/// successfully, so orchestrators don't route traffic before real data exists.
+async fn serve_health(exporter: web::Data<MetricsExporter>) -> HttpResponse {
+    if exporter.is_ready() {
+        HttpResponse::Ok().body("ok")
+    } else {
+        HttpResponse::ServiceUnavailable().body("no successful scrape yet")
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    let (endpoint, token) = load_config()?;
+    let (endpoint, token, interval_secs, config_path) = load_config()?;
+
+    let relabel_config = match &config_path {
+        Some(path) => RelabelConfig::load(Path::new(path))?,
+        None => RelabelConfig::default(),
+    };
 
     let client = UnifiClient::new(&endpoint, token).await?;
     println!("Authenticating...");
     client.authenticate().await?;
     println!("✅ Authenticated!");
 
-    println!("Iterating devices");
-    let devices: unifi::DevicesResponse = fetch_devices(&client).await?;
-
     let exporter: MetricsExporter = MetricsExporter::new()?;
 
-    for dev in &devices.data {
-        println!("\nStats for device: {dev_name}", dev_name = dev.name);
-        let raw_device_stats = client.get_device_stats(&dev.id.to_string()).await?;
-        let device_stats: unifi::DeviceStats = serde_json::from_value(raw_device_stats)
-            .map_err(|e| anyhow!("Failed to deserialize device stats response: {}", e))?;
-        exporter.update_device_metrics(dev.name.as_str(), &device_stats);
+    println!("Iterating devices");
+    let start = Instant::now();
+    let initial_result = poll_devices(&client, &exporter, &relabel_config).await;
+    exporter.record_scrape(start.elapsed(), initial_result.is_ok());
+    exporter.commit_scrape(initial_result.is_ok());
+    if let Err(e) = initial_result {
+        eprintln!("⚠️ Initial scrape failed, starting server anyway: {}", e);
     }
 
-    let res = exporter.render();
-
     let exporter_data = web::Data::new(exporter);
+    let client = Arc::new(client);
+    let relabel_config = Arc::new(relabel_config);
+
+    let poll_interval = Duration::from_secs(interval_secs);
+    println!("Polling every {}s", interval_secs);
+    tokio::spawn(poll_loop(
+        client.clone(),
+        exporter_data.clone(),
+        relabel_config.clone(),
+        poll_interval,
+    ));
 
     // Needs to be thread safe, so we can clone the data for each thread.
     HttpServer::new(move || {
         App::new()
             .app_data(exporter_data.clone())
             .route("/metrics", web::get().to(serve_metrics))
+            .route("/health", web::get().to(serve_health))
     })
     .bind("127.0.0.1:8080")?
     .run()
@@ -0,0 +1,138 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Operator-chosen overrides for a single device, keyed by device ID or MAC
+/// address in `RelabelConfig::devices`. Raw UniFi names are often unhelpful
+/// (MACs, model defaults), so this lets an operator supply a friendlier one
+/// plus a couple of static labels to group devices by role or location.
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct DeviceOverride {
+    /// Friendly name to report instead of the raw UniFi device name.
+    pub name: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+}
+
+/// Friendly-naming / relabeling config loaded from `--config`. Keys in `devices`
+/// may be either the UniFi device ID or its MAC address.
+#[derive(Debug, Deserialize, Default)]
+pub struct RelabelConfig {
+    #[serde(default)]
+    pub devices: HashMap<String, DeviceOverride>,
+}
+
+impl RelabelConfig {
+    /// Loads a relabel config from a TOML or YAML file, selected by extension
+    /// (`.yaml`/`.yml` for YAML, anything else as TOML).
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read relabel config {}", path.display()))?;
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&contents)
+                .with_context(|| format!("failed to parse relabel config {} as YAML", path.display())),
+            _ => toml::from_str(&contents)
+                .with_context(|| format!("failed to parse relabel config {} as TOML", path.display())),
+        }
+    }
+
+    /// Resolves the friendly name and extra labels for a device, falling back to
+    /// `raw_name` and empty labels when no override matches either the device ID
+    /// or MAC address.
+    pub fn resolve(&self, device_id: &str, mac_address: &str, raw_name: &str) -> ResolvedDevice {
+        let device_override = self
+            .devices
+            .get(device_id)
+            .or_else(|| self.devices.get(mac_address));
+
+        match device_override {
+            Some(o) => ResolvedDevice {
+                name: o.name.clone().unwrap_or_else(|| raw_name.to_string()),
+                role: o.role.clone().unwrap_or_default(),
+                location: o.location.clone().unwrap_or_default(),
+            },
+            None => ResolvedDevice {
+                name: raw_name.to_string(),
+                role: String::new(),
+                location: String::new(),
+            },
+        }
+    }
+}
+
+/// The friendly name and extra labels to apply to a device's metrics, as
+/// resolved by `RelabelConfig::resolve`.
+pub struct ResolvedDevice {
+    pub name: String,
+    pub role: String,
+    pub location: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with(key: &str, o: DeviceOverride) -> RelabelConfig {
+        let mut devices = HashMap::new();
+        devices.insert(key.to_string(), o);
+        RelabelConfig { devices }
+    }
+
+    #[test]
+    fn falls_back_to_raw_name_with_no_override() {
+        let config = RelabelConfig::default();
+        let resolved = config.resolve("dev-1", "aa:bb:cc:dd:ee:ff", "Switch-Lobby");
+        assert_eq!(resolved.name, "Switch-Lobby");
+        assert_eq!(resolved.role, "");
+        assert_eq!(resolved.location, "");
+    }
+
+    #[test]
+    fn matches_override_by_device_id() {
+        let config = config_with(
+            "dev-1",
+            DeviceOverride {
+                name: Some("Lobby AP".to_string()),
+                role: Some("ap".to_string()),
+                location: Some("lobby".to_string()),
+            },
+        );
+        let resolved = config.resolve("dev-1", "aa:bb:cc:dd:ee:ff", "Switch-Lobby");
+        assert_eq!(resolved.name, "Lobby AP");
+        assert_eq!(resolved.role, "ap");
+        assert_eq!(resolved.location, "lobby");
+    }
+
+    #[test]
+    fn matches_override_by_mac_address_when_id_misses() {
+        let config = config_with(
+            "aa:bb:cc:dd:ee:ff",
+            DeviceOverride {
+                name: Some("Lobby AP".to_string()),
+                ..Default::default()
+            },
+        );
+        let resolved = config.resolve("dev-1", "aa:bb:cc:dd:ee:ff", "Switch-Lobby");
+        assert_eq!(resolved.name, "Lobby AP");
+    }
+
+    #[test]
+    fn unset_override_fields_fall_back_to_defaults() {
+        let config = config_with(
+            "dev-1",
+            DeviceOverride {
+                name: None,
+                role: Some("ap".to_string()),
+                location: None,
+            },
+        );
+        let resolved = config.resolve("dev-1", "aa:bb:cc:dd:ee:ff", "Switch-Lobby");
+        assert_eq!(resolved.name, "Switch-Lobby");
+        assert_eq!(resolved.role, "ap");
+        assert_eq!(resolved.location, "");
+    }
+}
@@ -1,32 +1,76 @@
-use anyhow::{Result, anyhow};
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use thiserror::Error;
 use url::Url;
 
-let API_PATH_INFO: String = String::from("/proxy/network/integration/v1/info");
+const API_PATH_INFO: &str = "/proxy/network/integration/v1/info";
 const API_PATH_SITES: &str = "/proxy/network/integration/v1/sites";
 const API_PATH_DEVICES: &str = "/proxy/network/integration/v1/sites/{site_id}/devices";
 const API_PATH_DEVICE_STATS: &str =
     "/proxy/network/integration/v1/sites/{site_id}/devices/{device_id}/statistics/latest";
 
+/// Integration API version this crate has been built against. `UnifiClient::new`
+/// refuses to talk to a controller reporting anything else, so a breaking API
+/// change fails fast with `UnsupportedApiVersion` instead of an opaque deserialize
+/// error further down the line.
+const SUPPORTED_API_VERSION: u64 = 1;
+
+#[derive(Debug, Error)]
+pub enum UnifiError {
+    #[error("controller reports unsupported integration API version {0}")]
+    UnsupportedApiVersion(u64),
+
+    #[error("request to Unifi controller failed: {0}")]
+    Request(#[from] reqwest::Error),
+
+    #[error("failed to decode Unifi controller response: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    #[error("failed to construct Unifi controller URL: {0}")]
+    Url(#[from] url::ParseError),
+
+    #[error("Unifi controller returned {status}: {body}")]
+    Response { status: StatusCode, body: String },
+}
+
+pub type Result<T> = std::result::Result<T, UnifiError>;
+
+/// Given the number of items `fetched` in a page and the running `offset`,
+/// returns the offset to fetch next, or `None` once `total_count` items have
+/// been collected (or the controller returned an empty page, in case
+/// `total_count` is wrong or missing).
+fn next_page_offset(offset: u32, fetched: u32, total_count: u32) -> Option<u32> {
+    let next_offset = offset + fetched;
+    if fetched == 0 || next_offset >= total_count {
+        None
+    } else {
+        Some(next_offset)
+    }
+}
+
 pub struct UnifiClient {
     client: Client,
     endpoint: Url,
     api_token: String,
-    site_id: String,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct InfoResponse {
+    pub application_version: String,
+    pub api_version: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Site {
     pub id: String,
     pub internal_reference: String,
     pub name: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SitesResponse {
     pub offset: u32,
@@ -124,32 +168,68 @@ impl UnifiClient {
     /// * `api_token` - The API token for Unifi controller authentication.
     ///
     /// # Returns
-    /// A `Result` containing the `UnifiClient` instance or an `anyhow::Error` if the endpoint URL is invalid.
+    /// A `Result` containing the `UnifiClient` instance, `UnifiError::UnsupportedApiVersion`
+    /// if the controller speaks an integration API version this crate doesn't support, or
+    /// another `UnifiError` if the endpoint URL is invalid or unreachable.
     pub async fn new(endpoint_str: &str, api_token: String) -> Result<Self> {
         let endpoint = Url::parse(endpoint_str)?;
-        let site_id: String = "".to_string();
 
         let client = Client::builder()
             .danger_accept_invalid_certs(true)
             .build()?;
 
-        let mut unifi = Self {
+        let unifi = Self {
             client,
             endpoint,
             api_token,
-            site_id: "".to_string(),
         };
 
-        unifi.fetch_and_set_site_id().await?;
+        unifi.verify_api_version().await?;
 
         Ok(unifi)
     }
 
+    /// Hits `API_PATH_INFO` and confirms the controller reports an integration API
+    /// version this crate was built against, failing fast with
+    /// `UnifiError::UnsupportedApiVersion` rather than an opaque deserialize error
+    /// further down the line.
+    async fn verify_api_version(&self) -> Result<()> {
+        let info_url = self.endpoint.join(API_PATH_INFO)?;
+
+        println!("Checking integration API version at: {}", info_url);
+
+        let response = self
+            .client
+            .get(info_url)
+            .header("X-API-KEY", &self.api_token)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Failed to read response body".to_string());
+            return Err(UnifiError::Response { status, body });
+        }
+
+        let body = response.text().await?;
+        let info: InfoResponse = serde_json::from_str(&body).map_err(UnifiError::Decode)?;
+
+        if info.api_version != SUPPORTED_API_VERSION {
+            return Err(UnifiError::UnsupportedApiVersion(info.api_version));
+        }
+
+        println!(
+            "✅ Controller reports integration API version {} (application {})",
+            info.api_version, info.application_version
+        );
+        Ok(())
+    }
+
     pub async fn authenticate(&self) -> Result<()> {
-        let test_url = self
-            .endpoint
-            .join(API_PATH_INFO)
-            .map_err(|e| anyhow!("Failed to construct test URL: {}", e))?;
+        let test_url = self.endpoint.join(API_PATH_INFO)?;
 
         println!(
             "Attempting to authenticate with Unifi controller at: {}",
@@ -172,18 +252,15 @@ impl UnifiClient {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Failed to read response body".to_string());
-            Err(anyhow!(
-                "API Token authentication failed! Status: {}, Body: {}",
-                status,
-                body
-            ))
+            Err(UnifiError::Response { status, body })
         }
     }
-    pub async fn get_sites(&self) -> Result<Value> {
-        let sites_url = self
-            .endpoint
-            .join(API_PATH_SITES)
-            .map_err(|e| anyhow!("Failed to construct sites URL: {}", e))?;
+    /// Fetches a single page of sites starting at `offset`.
+    async fn get_sites_page(&self, offset: u32) -> Result<Value> {
+        let mut sites_url = self.endpoint.join(API_PATH_SITES)?;
+        sites_url
+            .query_pairs_mut()
+            .append_pair("offset", &offset.to_string());
 
         println!("Fetching sites from: {}", sites_url);
 
@@ -195,7 +272,8 @@ impl UnifiClient {
             .await?;
 
         if response.status().is_success() {
-            let sites_body: Value = response.json().await?;
+            let body = response.text().await?;
+            let sites_body: Value = serde_json::from_str(&body).map_err(UnifiError::Decode)?;
             Ok(sites_body)
         } else {
             let status = response.status();
@@ -203,53 +281,51 @@ impl UnifiClient {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Failed to read response body".to_string());
-            Err(anyhow!(
-                "Failed to fetch sites! Status: {}, Body: {}",
-                status,
-                body
-            ))
+            Err(UnifiError::Response { status, body })
         }
     }
 
-    pub async fn fetch_and_set_site_id(&mut self) -> Result<()> {
-        println!("ðŸ“¡ Fetching sites...");
-        let sites = self.get_sites().await?;
-        println!("{}", serde_json::to_string_pretty(&sites)?);
-
-        let site_id = sites["data"]
-            .as_array()
-            .and_then(|arr| arr.first())
-            .and_then(|site| site["id"].as_str());
-
-        match site_id {
-            Some(id) => {
-                println!("âœ… Using site ID: {}", id);
-                self.site_id = id.to_string();
-                Ok(())
+    /// Fetches every site known to the controller, typed as `SitesResponse`, paging
+    /// through `offset`/`limit` until `totalCount` sites have been collected so
+    /// large controllers aren't silently truncated at the API's default page size.
+    pub async fn get_all_sites(&self) -> Result<SitesResponse> {
+        let mut data = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page: SitesResponse = serde_json::from_value(self.get_sites_page(offset).await?)
+                .map_err(UnifiError::Decode)?;
+            let fetched = page.data.len() as u32;
+            data.extend(page.data);
+
+            match next_page_offset(offset, fetched, page.total_count) {
+                Some(next_offset) => offset = next_offset,
+                None => break,
             }
-            None => Err(anyhow!("âŒ No site ID found in response")),
         }
+
+        let total = data.len() as u32;
+        Ok(SitesResponse {
+            offset: 0,
+            limit: total,
+            count: total,
+            total_count: total,
+            data,
+        })
     }
 
-    /// Fetches the list of devices for a specific site from the Unifi controller.
-    ///
-    /// # Arguments
-    /// * `site_id` - The ID of the Unifi site (e.g., "default").
-    ///
-    /// # Returns
-    /// A `Result` containing a `serde_json::Value` representing the devices data,
-    /// or an `anyhow::Error` on failure.
-    pub async fn get_devices(&self) -> Result<Value> {
+    /// Fetches a single page of devices for a specific site, starting at `offset`.
+    async fn get_devices_page(&self, site_id: &str, offset: u32) -> Result<Value> {
         // I don't _love_ this, I feel like I'm fighting against the language here. But for now...
-        let relative_path = API_PATH_DEVICES.replace("{site_id}", self.site_id.as_ref());
-        let devices_url = self
-            .endpoint
-            .join(&relative_path)
-            .map_err(|e| anyhow!("Failed to construct devices URL: {}", e))?;
+        let relative_path = API_PATH_DEVICES.replace("{site_id}", site_id);
+        let mut devices_url = self.endpoint.join(&relative_path)?;
+        devices_url
+            .query_pairs_mut()
+            .append_pair("offset", &offset.to_string());
 
         println!(
             "Fetching devices for site '{}' from: {}",
-            self.site_id, devices_url
+            site_id, devices_url
         );
 
         let response = self
@@ -260,7 +336,8 @@ impl UnifiClient {
             .await?;
 
         if response.status().is_success() {
-            let devices_body: Value = response.json().await?;
+            let body = response.text().await?;
+            let devices_body: Value = serde_json::from_str(&body).map_err(UnifiError::Decode)?;
             Ok(devices_body)
         } else {
             let status = response.status();
@@ -268,24 +345,53 @@ impl UnifiClient {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Failed to read response body".to_string());
-            Err(anyhow!(
-                "Failed to fetch devices for site '{}'! Status: {}, Body: {}",
-                self.site_id,
-                status,
-                body
-            ))
+            Err(UnifiError::Response { status, body })
+        }
+    }
+
+    /// Fetches every device for a specific site, paging through `offset`/`limit`
+    /// until `totalCount` devices have been collected.
+    ///
+    /// # Arguments
+    /// * `site_id` - The ID of the Unifi site (e.g., "default").
+    ///
+    /// # Returns
+    /// A `Result` containing a `serde_json::Value` representing the fully-paginated
+    /// devices data, or a `UnifiError` on failure.
+    pub async fn get_devices(&self, site_id: &str) -> Result<Value> {
+        let mut data = Vec::new();
+        let mut offset = 0;
+
+        loop {
+            let page: DevicesResponse =
+                serde_json::from_value(self.get_devices_page(site_id, offset).await?)
+                    .map_err(UnifiError::Decode)?;
+            let fetched = page.data.len() as u32;
+            data.extend(page.data);
+
+            match next_page_offset(offset, fetched, page.total_count) {
+                Some(next_offset) => offset = next_offset,
+                None => break,
+            }
         }
+
+        let total = data.len() as u32;
+        serde_json::to_value(DevicesResponse {
+            count: total,
+            data,
+            limit: total,
+            offset: 0,
+            total_count: total,
+        })
+        .map_err(UnifiError::Decode)
     }
 
-    pub async fn get_device_stats(&self, device_id: &str) -> Result<Value> {
-        let mut relative_path = API_PATH_DEVICE_STATS.replace("{site_id}", self.site_id.as_ref());
+    pub async fn get_device_stats(&self, site_id: &str, device_id: &str) -> Result<Value> {
+        let mut relative_path = API_PATH_DEVICE_STATS.replace("{site_id}", site_id);
 
         relative_path = relative_path.replace("{device_id}", device_id);
 
-        let device_stats_url = self
-            .endpoint
-            .join(&relative_path)
-            .map_err(|e| anyhow!("Failed to fetch device stats from URL: {}", e))?;
+        let device_stats_url = self.endpoint.join(&relative_path)?;
 
         let response = self
             .client
@@ -295,7 +401,9 @@ impl UnifiClient {
             .await?;
 
         if response.status().is_success() {
-            let device_stats_body: Value = response.json().await?;
+            let body = response.text().await?;
+            let device_stats_body: Value =
+                serde_json::from_str(&body).map_err(UnifiError::Decode)?;
             Ok(device_stats_body)
         } else {
             let status = response.status();
@@ -303,12 +411,34 @@ impl UnifiClient {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Failed to read response body".to_string());
-            Err(anyhow!(
-                "Faild to fetch device stats for: '{}'! Status '{}' Body: '{}'",
-                device_id,
-                status,
-                body
-            ))
+            Err(UnifiError::Response { status, body })
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_until_total_count_reached() {
+        assert_eq!(next_page_offset(0, 25, 60), Some(25));
+        assert_eq!(next_page_offset(25, 25, 60), Some(50));
+        assert_eq!(next_page_offset(50, 10, 60), None);
+    }
+
+    #[test]
+    fn stops_on_exact_total_count() {
+        assert_eq!(next_page_offset(0, 60, 60), None);
+    }
+
+    #[test]
+    fn stops_on_empty_page_even_if_total_count_not_reached() {
+        assert_eq!(next_page_offset(30, 0, 60), None);
+    }
+
+    #[test]
+    fn stops_when_total_count_is_zero() {
+        assert_eq!(next_page_offset(0, 0, 0), None);
+    }
+}